@@ -8,6 +8,7 @@ use euclid::default::Size2D;
 
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::ffi::c_void;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
@@ -30,6 +31,178 @@ use surfman_chains::SwapChain;
 
 use webrender_api::units::TexelRect;
 
+mod dmabuf {
+    //! Raw `EGL_LINUX_DMA_BUF_EXT` import. Not wrapped by `surfman` yet,
+    //! so this talks to EGL/GL directly.
+
+    use super::{DmabufPlane, DrmFourcc};
+    use euclid::default::Size2D;
+    use std::ffi::c_void;
+    use surfman::Error;
+
+    #[cfg(target_os = "linux")]
+    pub(super) fn import(
+        planes: &[DmabufPlane],
+        format: DrmFourcc,
+        modifier: u64,
+        size: Size2D<i32>,
+        gl_egl_image_target_texture_2d_oes: *const c_void,
+    ) -> Result<u32, Error> {
+        super::linux::import(
+            planes,
+            format,
+            modifier,
+            size,
+            gl_egl_image_target_texture_2d_oes,
+        )
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub(super) fn import(
+        _planes: &[DmabufPlane],
+        _format: DrmFourcc,
+        _modifier: u64,
+        _size: Size2D<i32>,
+        _gl_egl_image_target_texture_2d_oes: *const c_void,
+    ) -> Result<u32, Error> {
+        Err(Error::Failed)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub(super) fn delete_texture(texture: u32) {
+        super::linux::delete_texture(texture)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub(super) fn delete_texture(_texture: u32) {}
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::{DmabufPlane, DrmFourcc};
+    use euclid::default::Size2D;
+    use std::ffi::c_void;
+    use std::os::raw::c_int;
+    use std::ptr;
+    use surfman::Error;
+
+    type EGLDisplay = *mut c_void;
+    type EGLContext = *mut c_void;
+    type EGLImageKHR = *mut c_void;
+    type EGLenum = u32;
+    type EGLint = i32;
+
+    // The `GL_OES_EGL_image` entry point used to bind an `EGLImageKHR` to
+    // a texture; resolved dynamically since it's a GLES/EGL extension,
+    // not a core entry point every GL library exports for direct linking.
+    type PfnGlEglImageTargetTexture2DOes = unsafe extern "C" fn(target: u32, image: EGLImageKHR);
+
+    const EGL_LINUX_DMA_BUF_EXT: EGLenum = 0x3270;
+    const EGL_WIDTH: EGLint = 0x3057;
+    const EGL_HEIGHT: EGLint = 0x3056;
+    const EGL_LINUX_DRM_FOURCC_EXT: EGLint = 0x3271;
+    const EGL_NONE: EGLint = 0x3038;
+    const GL_TEXTURE_2D: u32 = 0x0DE1;
+
+    // EGL_EXT_image_dma_buf_import{,_modifiers}: fd/offset/pitch and
+    // modifier attributes, indexed by plane.
+    const PLANE_FD: [EGLint; 3] = [0x3272, 0x3275, 0x3278];
+    const PLANE_OFFSET: [EGLint; 3] = [0x3273, 0x3276, 0x3279];
+    const PLANE_PITCH: [EGLint; 3] = [0x3274, 0x3277, 0x327A];
+    const PLANE_MODIFIER_LO: [EGLint; 3] = [0x3443, 0x3445, 0x3447];
+    const PLANE_MODIFIER_HI: [EGLint; 3] = [0x3444, 0x3446, 0x3448];
+
+    #[link(name = "EGL")]
+    extern "C" {
+        fn eglGetCurrentDisplay() -> EGLDisplay;
+        fn eglCreateImageKHR(
+            dpy: EGLDisplay,
+            ctx: EGLContext,
+            target: EGLenum,
+            buffer: *mut c_void,
+            attrib_list: *const EGLint,
+        ) -> EGLImageKHR;
+        fn eglDestroyImageKHR(dpy: EGLDisplay, image: EGLImageKHR) -> u32;
+    }
+
+    #[link(name = "GL")]
+    extern "C" {
+        fn glGenTextures(n: c_int, textures: *mut u32);
+        fn glBindTexture(target: u32, texture: u32);
+        fn glDeleteTextures(n: c_int, textures: *const u32);
+    }
+
+    /// Build an `EGLImageKHR` from `planes` via `EGL_LINUX_DMA_BUF_EXT`
+    /// and bind it to a fresh GL texture via `gl_egl_image_target_texture_2d_oes`
+    /// (the caller's already-resolved `glEGLImageTargetTexture2DOES`,
+    /// e.g. from `eglGetProcAddress`), without a CPU copy. Requires a GL
+    /// context already current on this thread. The returned texture must
+    /// later be passed to [`delete_texture`].
+    #[allow(unsafe_code)]
+    pub(crate) fn import(
+        planes: &[DmabufPlane],
+        format: DrmFourcc,
+        modifier: u64,
+        size: Size2D<i32>,
+        gl_egl_image_target_texture_2d_oes: *const c_void,
+    ) -> Result<u32, Error> {
+        if planes.is_empty() || planes.len() > 3 || gl_egl_image_target_texture_2d_oes.is_null() {
+            return Err(Error::Failed);
+        }
+        let gl_egl_image_target_texture_2d_oes: PfnGlEglImageTargetTexture2DOes =
+            unsafe { std::mem::transmute(gl_egl_image_target_texture_2d_oes) };
+        let modifier_lo = (modifier & 0xffff_ffff) as EGLint;
+        let modifier_hi = (modifier >> 32) as EGLint;
+        let mut attribs = vec![
+            EGL_WIDTH,
+            size.width,
+            EGL_HEIGHT,
+            size.height,
+            EGL_LINUX_DRM_FOURCC_EXT,
+            format as EGLint,
+        ];
+        for (i, plane) in planes.iter().enumerate() {
+            attribs.extend_from_slice(&[PLANE_FD[i], plane.fd as EGLint]);
+            attribs.extend_from_slice(&[PLANE_OFFSET[i], plane.offset as EGLint]);
+            attribs.extend_from_slice(&[PLANE_PITCH[i], plane.stride as EGLint]);
+            attribs.extend_from_slice(&[PLANE_MODIFIER_LO[i], modifier_lo]);
+            attribs.extend_from_slice(&[PLANE_MODIFIER_HI[i], modifier_hi]);
+        }
+        attribs.push(EGL_NONE);
+
+        unsafe {
+            let display = eglGetCurrentDisplay();
+            if display.is_null() {
+                return Err(Error::Failed);
+            }
+            let image = eglCreateImageKHR(
+                display,
+                ptr::null_mut(),
+                EGL_LINUX_DMA_BUF_EXT,
+                ptr::null_mut(),
+                attribs.as_ptr(),
+            );
+            if image.is_null() {
+                return Err(Error::Failed);
+            }
+            let mut texture = 0;
+            glGenTextures(1, &mut texture);
+            glBindTexture(GL_TEXTURE_2D, texture);
+            gl_egl_image_target_texture_2d_oes(GL_TEXTURE_2D, image);
+            // The GL texture keeps its own reference; the EGLImage itself
+            // isn't needed once it's bound.
+            eglDestroyImageKHR(display, image);
+            Ok(texture)
+        }
+    }
+
+    /// Delete a texture returned by [`import`].
+    #[allow(unsafe_code)]
+    pub(crate) fn delete_texture(texture: u32) {
+        unsafe { glDeleteTextures(1, &texture) };
+    }
+}
+
 /// This trait is used as a bridge between the different GL clients
 /// in Servo that handles WebRender ExternalImages and the WebRender
 /// ExternalImageHandler API.
@@ -37,14 +210,113 @@ use webrender_api::units::TexelRect;
 /// This trait is used to notify lock/unlock messages and get the
 /// required info that WR needs.
 pub trait WebrenderExternalImageApi {
-    fn lock(&mut self, id: u64) -> (u32, Size2D<i32>);
+    /// Lock the image identified by `id` and return the texture backing
+    /// `channel_index` together with that plane's dimensions.
+    ///
+    /// For single-plane images (WebGL canvases, RGBA video frames)
+    /// `channel_index` is always `0`. Multi-planar YUV video frames
+    /// expose the luma plane on channel `0` and the chroma plane(s) on
+    /// channels `1..=2`; chroma planes are typically half-resolution
+    /// for 4:2:0 formats, so the returned size may differ from the
+    /// luma plane's size.
+    fn lock(&mut self, id: u64, channel_index: u8) -> (u32, Size2D<i32>);
     fn unlock(&mut self, id: u64);
 }
 
 /// Type of Webrender External Image Handler.
+#[derive(Clone)]
 pub enum WebrenderImageHandlerType {
     WebGL,
     Media,
+    ExternalBuffer,
+    WebGPU,
+    /// Wraps another handler type whose image is too large for the
+    /// GPU's max texture size and has been split into tiles; see
+    /// [`WebrenderExternalImageRegistry::next_tiled_ids`].
+    Tiled(Box<WebrenderImageHandlerType>, TiledImageDescriptor),
+}
+
+/// A DRM FourCC pixel format code, as found in `<drm_fourcc.h>` (e.g.
+/// `NV12`, `YUV420`).
+pub type DrmFourcc = u32;
+
+/// One plane of an externally-allocated dmabuf-backed buffer; a
+/// multi-planar format (e.g. NV12, I420) has one `DmabufPlane` per plane.
+pub struct DmabufPlane {
+    /// The dmabuf file descriptor backing this plane.
+    pub fd: std::os::unix::io::RawFd,
+    /// Byte offset of this plane's data within `fd`.
+    pub offset: u32,
+    /// Row stride, in bytes, of this plane.
+    pub stride: u32,
+}
+
+/// Default tile edge, in pixels, used when splitting an oversized
+/// external image into tiles.
+pub const DEFAULT_TILE_SIZE: u32 = 512;
+
+/// Describes one tile of an external image split into a grid of tiles,
+/// each `tile_size` pixels on a side (the last row/column may be
+/// smaller, clamped to `full_size`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TiledImageDescriptor {
+    /// The `id` the underlying handler knows this image by; every tile
+    /// locks/unlocks through this same id, not the tile's own
+    /// `ExternalImageId`.
+    pub resource_id: u64,
+    /// Size, in pixels, of the whole, untiled image.
+    pub full_size: Size2D<i32>,
+    /// Edge length, in pixels, of a tile before clamping.
+    pub tile_size: u32,
+    /// This tile's `(column, row)` within the tile grid.
+    pub coord: (u32, u32),
+}
+
+impl TiledImageDescriptor {
+    /// The origin and size, in pixels, of this tile within `full_size`,
+    /// with the last row/column clamped so it doesn't run past the
+    /// image's edge.
+    fn bounds(&self) -> (euclid::default::Point2D<i32>, Size2D<i32>) {
+        let (col, row) = self.coord;
+        let origin = euclid::default::Point2D::new(
+            col as i32 * self.tile_size as i32,
+            row as i32 * self.tile_size as i32,
+        );
+        let size = Size2D::new(
+            (self.tile_size as i32).min(self.full_size.width - origin.x).max(0),
+            (self.tile_size as i32).min(self.full_size.height - origin.y).max(0),
+        );
+        (origin, size)
+    }
+
+    /// The `TexelRect` this tile should sample from within a
+    /// `plane_size`-sized plane, flipped vertically when `flip_v` (as
+    /// WebGL and WebGPU surfaces require).
+    fn uv(&self, plane_size: Size2D<i32>, flip_v: bool) -> TexelRect {
+        let (origin, tile_size) = self.bounds();
+        // Tile bounds are in full-size pixels; scale them to this plane,
+        // which may be smaller (e.g. a 4:2:0 chroma plane).
+        let sx = plane_size.width as f32 / self.full_size.width.max(1) as f32;
+        let sy = plane_size.height as f32 / self.full_size.height.max(1) as f32;
+        let (left, right) = (
+            origin.x as f32 * sx,
+            (origin.x + tile_size.width) as f32 * sx,
+        );
+        let (top, bottom) = (
+            origin.y as f32 * sy,
+            (origin.y + tile_size.height) as f32 * sy,
+        );
+        if flip_v {
+            TexelRect::new(
+                left,
+                plane_size.height as f32 - top,
+                right,
+                plane_size.height as f32 - bottom,
+            )
+        } else {
+            TexelRect::new(left, top, right, bottom)
+        }
+    }
 }
 
 /// List of Webrender external images to be shared among all external image
@@ -75,6 +347,38 @@ impl WebrenderExternalImageRegistry {
         key
     }
 
+    /// Register a `full_size` image, already known to the `handler_type`
+    /// handler as `resource_id`, as a grid of tiles no larger than
+    /// `tile_size` pixels on a side, returning one `ExternalImageId` per
+    /// tile in row-major order; every returned id locks/unlocks through
+    /// `resource_id`.
+    pub fn next_tiled_ids(
+        &mut self,
+        handler_type: WebrenderImageHandlerType,
+        resource_id: u64,
+        full_size: Size2D<i32>,
+        tile_size: u32,
+    ) -> Vec<webrender_api::ExternalImageId> {
+        let tile_size = tile_size.max(1);
+        let cols = (full_size.width as u32 + tile_size - 1) / tile_size;
+        let rows = (full_size.height as u32 + tile_size - 1) / tile_size;
+        let mut ids = Vec::with_capacity((cols * rows) as usize);
+        for row in 0..rows {
+            for col in 0..cols {
+                let descriptor = TiledImageDescriptor {
+                    resource_id,
+                    full_size,
+                    tile_size,
+                    coord: (col, row),
+                };
+                let tiled_type =
+                    WebrenderImageHandlerType::Tiled(Box::new(handler_type.clone()), descriptor);
+                ids.push(self.next_id(tiled_type));
+            }
+        }
+        ids
+    }
+
     pub fn remove(&mut self, key: &webrender_api::ExternalImageId) {
         self.external_images.remove(key);
     }
@@ -90,6 +394,10 @@ pub struct WebrenderExternalImageHandlers {
     webgl_handler: Option<Box<dyn WebrenderExternalImageApi>>,
     /// Media player handler.
     media_handler: Option<Box<dyn WebrenderExternalImageApi>>,
+    /// Externally-allocated buffer handler (e.g. zero-copy dmabuf import).
+    external_buffer_handler: Option<Box<dyn WebrenderExternalImageApi>>,
+    /// WebGPU presentation surface handler.
+    webgpu_handler: Option<Box<dyn WebrenderExternalImageApi>>,
     /// Webrender external images.
     external_images: Arc<Mutex<WebrenderExternalImageRegistry>>,
 }
@@ -101,6 +409,8 @@ impl WebrenderExternalImageHandlers {
             Self {
                 webgl_handler: None,
                 media_handler: None,
+                external_buffer_handler: None,
+                webgpu_handler: None,
                 external_images: external_images.clone(),
             },
             external_images,
@@ -115,6 +425,70 @@ impl WebrenderExternalImageHandlers {
         match handler_type {
             WebrenderImageHandlerType::WebGL => self.webgl_handler = Some(handler),
             WebrenderImageHandlerType::Media => self.media_handler = Some(handler),
+            WebrenderImageHandlerType::ExternalBuffer => {
+                self.external_buffer_handler = Some(handler)
+            },
+            WebrenderImageHandlerType::WebGPU => self.webgpu_handler = Some(handler),
+            // A tiled image is composited by whichever handler produced
+            // the untiled image; register the handler under that type.
+            WebrenderImageHandlerType::Tiled(inner, _) => self.set_handler(handler, *inner),
+        }
+    }
+
+    /// Lock `id`/`channel_index` through the handler for `handler_type`,
+    /// returning its native texture id, the size of the plane it
+    /// produced, and whether WebRender should sample it with a
+    /// flipped-V `TexelRect` (as WebGL and WebGPU surfaces do).
+    fn lock_plane(
+        &mut self,
+        handler_type: &WebrenderImageHandlerType,
+        id: u64,
+        channel_index: u8,
+    ) -> (u32, Size2D<i32>, bool) {
+        match handler_type {
+            WebrenderImageHandlerType::WebGL => {
+                let (texture_id, size) =
+                    self.webgl_handler.as_mut().unwrap().lock(id, channel_index);
+                (texture_id, size, true)
+            },
+            WebrenderImageHandlerType::Media => {
+                let (texture_id, size) =
+                    self.media_handler.as_mut().unwrap().lock(id, channel_index);
+                (texture_id, size, false)
+            },
+            WebrenderImageHandlerType::ExternalBuffer => {
+                let (texture_id, size) = self
+                    .external_buffer_handler
+                    .as_mut()
+                    .unwrap()
+                    .lock(id, channel_index);
+                (texture_id, size, false)
+            },
+            WebrenderImageHandlerType::WebGPU => {
+                let (texture_id, size) =
+                    self.webgpu_handler.as_mut().unwrap().lock(id, channel_index);
+                (texture_id, size, true)
+            },
+            // Every tile locks through the one resource id the handler
+            // actually knows about, not its own synthetic tile id.
+            WebrenderImageHandlerType::Tiled(inner, descriptor) => {
+                self.lock_plane(inner, descriptor.resource_id, channel_index)
+            },
+        }
+    }
+
+    /// Unlock `id` through the handler for `handler_type`.
+    fn unlock_plane(&mut self, handler_type: &WebrenderImageHandlerType, id: u64) {
+        match handler_type {
+            WebrenderImageHandlerType::WebGL => self.webgl_handler.as_mut().unwrap().unlock(id),
+            WebrenderImageHandlerType::Media => self.media_handler.as_mut().unwrap().unlock(id),
+            WebrenderImageHandlerType::ExternalBuffer => {
+                self.external_buffer_handler.as_mut().unwrap().unlock(id)
+            },
+            WebrenderImageHandlerType::WebGPU => self.webgpu_handler.as_mut().unwrap().unlock(id),
+            WebrenderImageHandlerType::Tiled(inner, descriptor) => {
+                self.unlock_plane(inner, descriptor.resource_id)
+            },
         }
     }
 }
@@ -127,28 +501,29 @@ impl webrender_api::ExternalImageHandler for WebrenderExternalImageHandlers {
     fn lock(
         &mut self,
         key: webrender_api::ExternalImageId,
-        _channel_index: u8,
+        channel_index: u8,
         _rendering: webrender_api::ImageRendering,
     ) -> webrender_api::ExternalImage {
         let external_images = self.external_images.lock().unwrap();
         let handler_type = external_images
             .get(&key)
-            .expect("Tried to get unknown external image");
-        let (texture_id, uv) = match handler_type {
-            WebrenderImageHandlerType::WebGL => {
-                let (texture_id, size) = self.webgl_handler.as_mut().unwrap().lock(key.0);
-                (
-                    texture_id,
-                    TexelRect::new(0.0, size.height as f32, size.width as f32, 0.0),
-                )
-            },
-            WebrenderImageHandlerType::Media => {
-                let (texture_id, size) = self.media_handler.as_mut().unwrap().lock(key.0);
-                (
-                    texture_id,
-                    TexelRect::new(0.0, 0.0, size.width as f32, size.height as f32),
-                )
-            },
+            .expect("Tried to get unknown external image")
+            .clone();
+        drop(external_images);
+        let (texture_id, size, flip_v) = self.lock_plane(&handler_type, key.0, channel_index);
+        // WebRender locks once per plane of a multi-planar image
+        // descriptor: channel 0 is the luma (Y) plane, channels 1..=2 are
+        // the chroma planes (UV for NV12, U and V for I420). Each plane
+        // can have its own size, so the UVs below are derived from the
+        // plane that was actually returned rather than assumed to match
+        // the luma plane.
+        let uv = match &handler_type {
+            // The same full-size texture backs every tile; only the
+            // sampled sub-rect differs, clamped to the image's edge for
+            // the last row/column of tiles.
+            WebrenderImageHandlerType::Tiled(_, descriptor) => descriptor.uv(size, flip_v),
+            _ if flip_v => TexelRect::new(0.0, size.height as f32, size.width as f32, 0.0),
+            _ => TexelRect::new(0.0, 0.0, size.width as f32, size.height as f32),
         };
         webrender_api::ExternalImage {
             uv,
@@ -162,14 +537,32 @@ impl webrender_api::ExternalImageHandler for WebrenderExternalImageHandlers {
         let external_images = self.external_images.lock().unwrap();
         let handler_type = external_images
             .get(&key)
-            .expect("Tried to get unknown external image");
-        match handler_type {
-            WebrenderImageHandlerType::WebGL => self.webgl_handler.as_mut().unwrap().unlock(key.0),
-            WebrenderImageHandlerType::Media => self.media_handler.as_mut().unwrap().unlock(key.0),
-        };
+            .expect("Tried to get unknown external image")
+            .clone();
+        drop(external_images);
+        self.unlock_plane(&handler_type, key.0);
     }
 }
 
+/// Controls how `WebrenderSurfman::present` hands a finished back buffer
+/// off to the compositor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PresentMode {
+    /// Present buffers in order, then render into a spare or else the
+    /// oldest presented buffer; does not wait for the compositor to
+    /// release that buffer.
+    Fifo,
+    /// Keep at most one presented buffer queued for reuse, destroying and
+    /// replacing any older one; every frame is still presented, none is
+    /// dropped.
+    Mailbox,
+    /// Present the just-finished buffer immediately, without queueing.
+    Immediate,
+}
+
+/// Default number of buffers in the swap chain when none is specified.
+pub const DEFAULT_BUFFER_COUNT: usize = 2;
+
 /// A bridge between webrender and surfman
 // TODO: move this into a different crate so that script doesn't depend on surfman
 #[derive(Clone)]
@@ -180,6 +573,19 @@ struct WebrenderSurfmanData {
     context: RefCell<Context>,
     // We either render to a swap buffer or to a native widget
     swap_chain: Option<SwapChain<Device>>,
+    // How `present` should recycle back buffers on the native-widget path.
+    // The `swap_chain` path manages its own buffering and ignores this.
+    present_mode: PresentMode,
+    // Spare buffers, never yet bound to `context`, available to be
+    // rendered into next. Only populated (and consulted) on the
+    // native-widget path; drained before `queued_buffers` so every
+    // configured buffer gets used before any recycling kicks in.
+    back_buffers: RefCell<VecDeque<Surface>>,
+    // Buffers that have actually been through `present_surface` and are
+    // waiting to be rendered into again; `Mailbox` caps this at one,
+    // destroying (and replacing) whichever buffer it displaces, so a
+    // never-presented spare in `back_buffers` is never touched.
+    queued_buffers: RefCell<VecDeque<Surface>>,
 }
 
 impl Drop for WebrenderSurfmanData {
@@ -189,6 +595,12 @@ impl Drop for WebrenderSurfmanData {
         if let Some(ref swap_chain) = self.swap_chain {
             let _ = swap_chain.destroy(device, context);
         }
+        for mut surface in self.back_buffers.borrow_mut().drain(..) {
+            let _ = device.destroy_surface(context, &mut surface);
+        }
+        for mut surface in self.queued_buffers.borrow_mut().drain(..) {
+            let _ = device.destroy_surface(context, &mut surface);
+        }
         let _ = device.destroy_context(context);
     }
 }
@@ -199,6 +611,28 @@ impl WebrenderSurfman {
         adapter: &Adapter,
         context_attributes: ContextAttributes,
         surface_type: SurfaceType<NativeWidget>,
+    ) -> Result<Self, Error> {
+        Self::create_with_present_mode(
+            connection,
+            adapter,
+            context_attributes,
+            surface_type,
+            PresentMode::Fifo,
+            DEFAULT_BUFFER_COUNT,
+        )
+    }
+
+    /// Like [`WebrenderSurfman::create`], but also picks the `present_mode`
+    /// and buffer count (clamped to at least `1`) for the native-widget
+    /// path; a headless `surface_type` is always buffered through its
+    /// `surfman_chains::SwapChain` instead.
+    pub fn create_with_present_mode(
+        connection: &Connection,
+        adapter: &Adapter,
+        context_attributes: ContextAttributes,
+        surface_type: SurfaceType<NativeWidget>,
+        present_mode: PresentMode,
+        buffer_count: usize,
     ) -> Result<Self, Error> {
         let mut device = connection.create_device(&adapter)?;
         let context_descriptor = device.create_context_descriptor(&context_attributes)?;
@@ -209,6 +643,7 @@ impl WebrenderSurfman {
             SurfaceType::Generic { .. } => true,
         };
         let surface = device.create_surface(&context, surface_access, surface_type)?;
+        let surface_size = device.surface_info(&surface).size;
         device
             .bind_surface_to_context(&mut context, surface)
             .map_err(|(err, mut surface)| {
@@ -225,12 +660,26 @@ impl WebrenderSurfman {
         } else {
             None
         };
+        let mut back_buffers = VecDeque::new();
+        if !headless {
+            for _ in 1..buffer_count.max(1) {
+                let surface = device.create_surface(
+                    &context,
+                    surface_access,
+                    SurfaceType::Generic { size: surface_size },
+                )?;
+                back_buffers.push_back(surface);
+            }
+        }
         let device = RefCell::new(device);
         let context = RefCell::new(context);
         let data = WebrenderSurfmanData {
             device,
             context,
             swap_chain,
+            present_mode,
+            back_buffers: RefCell::new(back_buffers),
+            queued_buffers: RefCell::new(VecDeque::new()),
         };
         Ok(WebrenderSurfman(Rc::new(data)))
     }
@@ -253,6 +702,45 @@ impl WebrenderSurfman {
         device.destroy_surface_texture(context, surface_texture)
     }
 
+    /// Import a dmabuf as a GL texture via `EGL_LINUX_DMA_BUF_EXT`, with
+    /// no CPU copy. Requires `make_gl_context_current` to have been
+    /// called on this thread first. The returned texture must later be
+    /// passed to [`WebrenderSurfman::destroy_dmabuf_texture`].
+    pub fn import_dmabuf(
+        &self,
+        planes: &[DmabufPlane],
+        format: DrmFourcc,
+        modifier: u64,
+        size: Size2D<i32>,
+    ) -> Result<u32, Error> {
+        let gl_egl_image_target_texture_2d_oes =
+            self.get_proc_address("glEGLImageTargetTexture2DOES");
+        dmabuf::import(
+            planes,
+            format,
+            modifier,
+            size,
+            gl_egl_image_target_texture_2d_oes,
+        )
+    }
+
+    /// Delete a texture returned by [`WebrenderSurfman::import_dmabuf`].
+    pub fn destroy_dmabuf_texture(&self, texture: u32) {
+        dmabuf::delete_texture(texture)
+    }
+
+    /// Get the native GL texture id for `surface`'s current color
+    /// attachment, for registering a WebGPU presentation surface as a
+    /// WebRender external image.
+    pub fn present_surface_texture(
+        &self,
+        surface: Surface,
+    ) -> Result<(u32, SurfaceTexture), (Error, Surface)> {
+        let surface_texture = self.create_surface_texture(surface)?;
+        let texture_id = self.surface_texture_object(&surface_texture);
+        Ok((texture_id, surface_texture))
+    }
+
     pub fn make_gl_context_current(&self) -> Result<(), Error> {
         let ref device = self.0.device.borrow();
         let ref context = self.0.context.borrow();
@@ -266,7 +754,41 @@ impl WebrenderSurfman {
     pub fn resize(&self, size: Size2D<i32>) -> Result<(), Error> {
         let ref mut device = self.0.device.borrow_mut();
         let ref mut context = self.0.context.borrow_mut();
-        self.swap_chain()?.resize(device, context, size)
+        if self.0.swap_chain.is_some() {
+            return self.swap_chain()?.resize(device, context, size);
+        }
+        // Resize the surface currently bound to the context in place,
+        // not just the spares sitting in `back_buffers` — otherwise
+        // `Immediate` mode (which always rebinds the bound surface) sees
+        // no effect at all, and `Fifo`/`Mailbox` keep rotating a
+        // stale-sized surface back in until it happens to be one of the
+        // spares recreated below.
+        let mut bound = device.unbind_surface_from_context(context)?.unwrap();
+        device.resize_surface(&mut bound, size)?;
+        device
+            .bind_surface_to_context(context, bound)
+            .map_err(|(err, mut surface)| {
+                let _ = device.destroy_surface(context, &mut surface);
+                err
+            })?;
+
+        // Recycle every spare and queued back buffer at the new size too;
+        // nothing has been presented at the new size yet, so they all
+        // start over as plain spares.
+        let mut back_buffers = self.0.back_buffers.borrow_mut();
+        let mut queued_buffers = self.0.queued_buffers.borrow_mut();
+        let buffer_count = back_buffers.len() + queued_buffers.len();
+        for mut surface in back_buffers.drain(..).chain(queued_buffers.drain(..)) {
+            let _ = device.destroy_surface(context, &mut surface);
+        }
+        for _ in 0..buffer_count {
+            let surface =
+                device.create_surface(context, SurfaceAccess::GPUOnly, SurfaceType::Generic {
+                    size,
+                })?;
+            back_buffers.push_back(surface);
+        }
+        Ok(())
     }
 
     pub fn present(&self) -> Result<(), Error> {
@@ -275,10 +797,48 @@ impl WebrenderSurfman {
         if let Some(ref swap_chain) = self.0.swap_chain {
             return swap_chain.swap_buffers(device, context);
         }
-        let mut surface = device.unbind_surface_from_context(context)?.unwrap();
-        device.present_surface(context, &mut surface)?;
+        let mut finished = device.unbind_surface_from_context(context)?.unwrap();
+        device.present_surface(context, &mut finished)?;
+
+        let mut back_buffers = self.0.back_buffers.borrow_mut();
+        let mut queued_buffers = self.0.queued_buffers.borrow_mut();
+        let next = match self.0.present_mode {
+            // No queueing: keep rendering into the buffer we just presented.
+            PresentMode::Immediate => finished,
+            // Round-robin: prefer a never-used spare so every configured
+            // buffer gets used once before any reuse, then fall back to
+            // the oldest already-presented buffer.
+            PresentMode::Fifo => {
+                queued_buffers.push_back(finished);
+                back_buffers
+                    .pop_front()
+                    .unwrap_or_else(|| queued_buffers.pop_front().unwrap())
+            },
+            // Keep at most one already-presented buffer queued; an
+            // older one is destroyed and replaced with a fresh
+            // same-size spare, rather than simply dropped — the
+            // configured `buffer_count` must survive every call, not
+            // just the first one.
+            PresentMode::Mailbox => {
+                if let Some(mut stale) = queued_buffers.pop_front() {
+                    let size = device.surface_info(&stale).size;
+                    let _ = device.destroy_surface(context, &mut stale);
+                    if let Ok(surface) = device.create_surface(
+                        context,
+                        SurfaceAccess::GPUOnly,
+                        SurfaceType::Generic { size },
+                    ) {
+                        back_buffers.push_back(surface);
+                    }
+                }
+                queued_buffers.push_back(finished);
+                back_buffers
+                    .pop_front()
+                    .unwrap_or_else(|| queued_buffers.pop_front().unwrap())
+            },
+        };
         device
-            .bind_surface_to_context(context, surface)
+            .bind_surface_to_context(context, next)
             .map_err(|(err, mut surface)| {
                 let _ = device.destroy_surface(context, &mut surface);
                 err
@@ -335,3 +895,85 @@ impl WebrenderSurfman {
         device.get_proc_address(context, name)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_clamps_the_last_row_and_column() {
+        // A 500x260 image tiled at 256px: 2 columns, 2 rows, with the
+        // last column/row clamped to what's left of the image.
+        let descriptor = TiledImageDescriptor {
+            resource_id: 0,
+            full_size: Size2D::new(500, 260),
+            tile_size: 256,
+            coord: (1, 1),
+        };
+        let (origin, size) = descriptor.bounds();
+        assert_eq!(origin, euclid::default::Point2D::new(256, 256));
+        assert_eq!(size, Size2D::new(500 - 256, 260 - 256));
+    }
+
+    #[test]
+    fn bounds_is_unclamped_on_an_exact_multiple() {
+        // A 512x512 image tiled at 256px divides evenly, so every tile
+        // is a full `tile_size` square.
+        let descriptor = TiledImageDescriptor {
+            resource_id: 0,
+            full_size: Size2D::new(512, 512),
+            tile_size: 256,
+            coord: (1, 1),
+        };
+        let (origin, size) = descriptor.bounds();
+        assert_eq!(origin, euclid::default::Point2D::new(256, 256));
+        assert_eq!(size, Size2D::new(256, 256));
+    }
+
+    #[test]
+    fn uv_without_flip_matches_bounds() {
+        let descriptor = TiledImageDescriptor {
+            resource_id: 0,
+            full_size: Size2D::new(500, 260),
+            tile_size: 256,
+            coord: (1, 0),
+        };
+        let uv = descriptor.uv(Size2D::new(500, 260), false);
+        assert_eq!(uv, TexelRect::new(256.0, 0.0, 500.0, 256.0));
+    }
+
+    #[test]
+    fn uv_with_flip_mirrors_around_the_plane_height() {
+        let descriptor = TiledImageDescriptor {
+            resource_id: 0,
+            full_size: Size2D::new(500, 260),
+            tile_size: 256,
+            coord: (1, 1),
+        };
+        let uv = descriptor.uv(Size2D::new(500, 260), true);
+        assert_eq!(uv, TexelRect::new(256.0, 260.0 - 256.0, 500.0, 260.0 - 260.0));
+    }
+
+    #[test]
+    fn next_tiled_ids_covers_the_grid_in_row_major_order_with_one_resource_id() {
+        let mut registry = WebrenderExternalImageRegistry::new();
+        let ids = registry.next_tiled_ids(
+            WebrenderImageHandlerType::WebGL,
+            42,
+            Size2D::new(500, 260),
+            256,
+        );
+        // ceil(500/256) = 2 columns, ceil(260/256) = 2 rows.
+        assert_eq!(ids.len(), 4);
+        let expected_coords = [(0, 0), (1, 0), (0, 1), (1, 1)];
+        for (id, expected_coord) in ids.iter().zip(expected_coords.iter()) {
+            match registry.get(id).unwrap() {
+                WebrenderImageHandlerType::Tiled(_, descriptor) => {
+                    assert_eq!(descriptor.resource_id, 42);
+                    assert_eq!(descriptor.coord, *expected_coord);
+                },
+                _ => panic!("expected a Tiled handler type"),
+            }
+        }
+    }
+}